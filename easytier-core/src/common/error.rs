@@ -0,0 +1,11 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("shell command error: {0}")]
+    ShellCommandError(String),
+
+    #[error("unknown error")]
+    Unknown,
+
+    #[error("operation timed out: {0}")]
+    Timeout(String),
+}
@@ -0,0 +1,212 @@
+use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use tokio_util::bytes::Bytes;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Archive, RkyvSerialize, RkyvDeserialize,
+)]
+#[archive(check_bytes)]
+pub struct PeerIdWire(pub u128);
+
+impl From<uuid::Uuid> for PeerIdWire {
+    fn from(id: uuid::Uuid) -> Self {
+        PeerIdWire(id.as_u128())
+    }
+}
+
+impl From<PeerIdWire> for uuid::Uuid {
+    fn from(id: PeerIdWire) -> Self {
+        uuid::Uuid::from_u128(id.0)
+    }
+}
+
+// ttl a freshly originated rpc packet starts with; bounds how many hops a
+// request/response may be relayed through before it is dropped.
+pub const TARPC_DEFAULT_TTL: u8 = 32;
+
+// body of a `TaRpc` control packet: carries either a request or a response
+// (which may itself be an error produced on the server path).
+#[derive(Debug, Clone, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub struct TaRpcPacketBody {
+    pub service_id: u32,
+    pub is_req: bool,
+    // set on the response path when the server could not produce a normal
+    // reply (serialize/dispatch failure); `content` is then the encoded
+    // error message instead of the encoded rpc response. Also set, together
+    // with `is_req` and with empty `content`, on the cancellation frame a
+    // client sends when it gives up on a call.
+    pub is_err: bool,
+    // the peer that originally issued this request (for a request) or
+    // produced this response (for a response). Stays constant across relay
+    // hops, unlike `Packet::from_peer` which is rewritten to the relaying
+    // peer at every hop.
+    pub original_sender: PeerIdWire,
+    // remaining number of hops this packet may still be relayed through.
+    pub ttl: u8,
+    // sequence number of this frame within its request/response stream.
+    // unary calls always produce a single frame: `seq == 0` and
+    // `end_of_stream == true`.
+    pub seq: u32,
+    // whether this is the last frame of its stream.
+    pub end_of_stream: bool,
+    // whether `content` holds an actual serialized request/response item.
+    // `false` only for the synthetic terminator frame a streaming response
+    // ends with when it has no more items to send - distinct from a real
+    // item that happens to serialize to zero bytes (e.g. `()`, an empty
+    // `Vec<u8>`), which must still be yielded to the caller rather than
+    // mistaken for the terminator just because its `content` is also empty.
+    pub has_item: bool,
+    // id the call's originator assigned this call, echoed back unchanged on
+    // every response/stream frame it produces. `PeerRpcEndPoint`s are shared
+    // by every caller of a service, and tarpc's own per-request ids are only
+    // unique within one independent client - neither is safe to key a reply
+    // back to its requester by, so this is the one value both sides agree
+    // identifies "this particular outstanding call" end to end.
+    pub call_id: u64,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub enum CtrlPacketBody {
+    TaRpc(TaRpcPacketBody),
+}
+
+#[derive(Debug, Clone, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub enum PacketBody {
+    Ctrl(CtrlPacketBody),
+}
+
+#[derive(Debug, Clone, Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive(check_bytes)]
+pub struct Packet {
+    pub from_peer: PeerIdWire,
+    pub to_peer: Option<PeerIdWire>,
+    pub body: PacketBody,
+}
+
+impl Packet {
+    // build a freshly originated rpc packet, i.e. one whose `original_sender`
+    // is `from_peer` itself (we are the peer that produced this request or
+    // response, as opposed to a relay forwarding someone else's packet).
+    pub fn new_tarpc_packet(
+        from_peer: uuid::Uuid,
+        to_peer: uuid::Uuid,
+        service_id: u32,
+        is_req: bool,
+        call_id: u64,
+        content: Vec<u8>,
+    ) -> Self {
+        Self::new_tarpc_packet_with_err(
+            from_peer, to_peer, service_id, is_req, false, call_id, content,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_tarpc_packet_with_err(
+        from_peer: uuid::Uuid,
+        to_peer: uuid::Uuid,
+        service_id: u32,
+        is_req: bool,
+        is_err: bool,
+        call_id: u64,
+        content: Vec<u8>,
+    ) -> Self {
+        Self::new_tarpc_packet_relayed(
+            from_peer,
+            to_peer,
+            from_peer,
+            service_id,
+            is_req,
+            is_err,
+            TARPC_DEFAULT_TTL,
+            0,
+            true,
+            true,
+            call_id,
+            content,
+        )
+    }
+
+    // build a single frame of a streaming request/response: `seq` is this
+    // frame's position in the stream and `end_of_stream` marks the last one.
+    // `has_item` is false only for the synthetic terminator frame a
+    // streaming response sends once it has no more items (see
+    // `TaRpcPacketBody::has_item`); every other caller should pass `true`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_tarpc_stream_packet(
+        from_peer: uuid::Uuid,
+        to_peer: uuid::Uuid,
+        service_id: u32,
+        is_req: bool,
+        seq: u32,
+        end_of_stream: bool,
+        has_item: bool,
+        call_id: u64,
+        content: Vec<u8>,
+    ) -> Self {
+        Self::new_tarpc_packet_relayed(
+            from_peer,
+            to_peer,
+            from_peer,
+            service_id,
+            is_req,
+            false,
+            TARPC_DEFAULT_TTL,
+            seq,
+            end_of_stream,
+            has_item,
+            call_id,
+            content,
+        )
+    }
+
+    // build an rpc packet carrying an explicit `original_sender` and `ttl`,
+    // used both to originate a fresh packet (`original_sender == from_peer`)
+    // and to re-send a packet received from elsewhere to the next hop on its
+    // way to `to_peer` (`original_sender` preserved, `ttl` decremented).
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_tarpc_packet_relayed(
+        from_peer: uuid::Uuid,
+        to_peer: uuid::Uuid,
+        original_sender: uuid::Uuid,
+        service_id: u32,
+        is_req: bool,
+        is_err: bool,
+        ttl: u8,
+        seq: u32,
+        end_of_stream: bool,
+        has_item: bool,
+        call_id: u64,
+        content: Vec<u8>,
+    ) -> Self {
+        Self {
+            from_peer: from_peer.into(),
+            to_peer: Some(to_peer.into()),
+            body: PacketBody::Ctrl(CtrlPacketBody::TaRpc(TaRpcPacketBody {
+                service_id,
+                is_req,
+                is_err,
+                original_sender: original_sender.into(),
+                ttl,
+                seq,
+                end_of_stream,
+                has_item,
+                call_id,
+                content,
+            })),
+        }
+    }
+
+    pub fn decode(b: &Bytes) -> &ArchivedPacket {
+        unsafe { rkyv::archived_root::<Packet>(b) }
+    }
+}
+
+impl From<Packet> for Bytes {
+    fn from(p: Packet) -> Bytes {
+        let bytes = rkyv::to_bytes::<_, 1024>(&p).expect("failed to serialize packet");
+        Bytes::from(bytes.into_vec())
+    }
+}
@@ -1,7 +1,9 @@
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use dashmap::DashMap;
-use futures::{SinkExt, StreamExt};
+use futures::{future, SinkExt, Stream, StreamExt};
 use rkyv::Deserialize;
 use tarpc::{server::Channel, transport::channel::UnboundedChannel};
 use tokio::{
@@ -14,15 +16,24 @@ use tracing::Instrument;
 use crate::{common::error::Error, peers::packet::Packet};
 
 use super::packet::{CtrlPacketBody, PacketBody};
+use super::rpc_security::{PlaintextSecurity, SecureTransport, TransportSecurity};
 
 type PeerRpcServiceId = u32;
 
+// default idle window `run()` reaps `PeerRpcEndPoint`s after; see
+// `start_idle_reaper`.
+const DEFAULT_IDLE_ENDPOINT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
 #[async_trait::async_trait]
 #[auto_impl::auto_impl(Arc)]
 pub trait PeerRpcManagerTransport: Send + Sync + 'static {
     fn my_peer_id(&self) -> uuid::Uuid;
     async fn send(&self, msg: Bytes, dst_peer_id: &uuid::Uuid) -> Result<(), Error>;
     async fn recv(&self) -> Result<Bytes, Error>;
+    // next directly-connected peer to forward a packet addressed to
+    // `dst_peer_id` through, or `None` if we have no route to it. Used to
+    // relay rpc packets for peers we are not directly connected to.
+    fn get_next_hop(&self, dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid>;
 }
 
 type PacketSender = UnboundedSender<Packet>;
@@ -31,11 +42,25 @@ struct PeerRpcEndPoint {
     peer_id: uuid::Uuid,
     packet_sender: PacketSender,
     tasks: JoinSet<()>,
+    // last time a request packet was dispatched to this endpoint; read by
+    // the idle reaper spawned from `start_idle_reaper`.
+    last_active: Arc<std::sync::Mutex<std::time::Instant>>,
 }
 
 type PeerRpcEndPointCreator = Box<dyn Fn(uuid::Uuid) -> PeerRpcEndPoint + Send + Sync + 'static>;
-#[derive(Hash, Eq, PartialEq, Clone)]
-struct PeerRpcClientCtxKey(uuid::Uuid, PeerRpcServiceId);
+
+// id a client call assigns itself (see `TaRpcPacketBody::call_id`); the only
+// thing `client_resp_receivers` may be safely keyed on, since two concurrent
+// calls can otherwise share a `(dst_peer_id, service_id)` pair.
+type CallId = u64;
+
+// an outstanding client call waiting on a response: `dst_peer_id` is kept
+// alongside the sender so `remove_peer` can find every call a disconnected
+// peer owns without call_id itself encoding who it belongs to.
+struct ClientRespReceiver {
+    dst_peer_id: uuid::Uuid,
+    sender: PacketSender,
+}
 
 // handle rpc request from one peer
 pub struct PeerRpcManager {
@@ -46,7 +71,7 @@ pub struct PeerRpcManager {
     service_registry: Arc<DashMap<PeerRpcServiceId, PeerRpcEndPointCreator>>,
     peer_rpc_endpoints: Arc<DashMap<(uuid::Uuid, PeerRpcServiceId), PeerRpcEndPoint>>,
 
-    client_resp_receivers: Arc<DashMap<PeerRpcClientCtxKey, PacketSender>>,
+    client_resp_receivers: Arc<DashMap<CallId, ClientRespReceiver>>,
 }
 
 impl std::fmt::Debug for PeerRpcManager {
@@ -63,15 +88,33 @@ struct TaRpcPacketInfo {
     to_peer: uuid::Uuid,
     service_id: PeerRpcServiceId,
     is_req: bool,
+    is_err: bool,
+    original_sender: uuid::Uuid,
+    ttl: u8,
+    seq: u32,
+    end_of_stream: bool,
+    has_item: bool,
+    call_id: u64,
     content: Vec<u8>,
 }
 
 impl PeerRpcManager {
+    // plaintext rpc channel, no handshake or per-peer session overhead.
     pub fn new(tspt: impl PeerRpcManagerTransport) -> Self {
+        Self::new_with_security(tspt, Arc::new(PlaintextSecurity))
+    }
+
+    // rpc channel wrapped in the given `TransportSecurity` envelope, so
+    // deployments can opt into authenticated-only or encrypted rpc traffic
+    // independent of whatever tunnel carries it.
+    pub fn new_with_security(
+        tspt: impl PeerRpcManagerTransport,
+        security: Arc<dyn TransportSecurity>,
+    ) -> Self {
         Self {
             service_map: Arc::new(DashMap::new()),
             tasks: JoinSet::new(),
-            tspt: Arc::new(Box::new(tspt)),
+            tspt: Arc::new(Box::new(SecureTransport::new(tspt, security))),
 
             service_registry: Arc::new(DashMap::new()),
             peer_rpc_endpoints: Arc::new(DashMap::new()),
@@ -103,7 +146,25 @@ impl PeerRpcManager {
 
             let tspt = tspt.clone();
             tasks.spawn(async move {
-                let mut cur_req_uuid = None;
+                // this endpoint is shared by every caller of this service
+                // (see `run` keying `peer_rpc_endpoints` by
+                // `(to_peer, service_id)` alone), and tarpc can have more
+                // than one of their requests in flight at once against this
+                // one `client_transport`. A single "current" peer/sender
+                // pair isn't enough to route replies correctly once that
+                // happens - two overlapping requests would race to
+                // overwrite it and a reply could be sent to the wrong
+                // caller (or dropped). Track each request's routing info
+                // per request instead. tarpc's own per-request ids are only
+                // unique within one client and every caller brings its own
+                // client, so each incoming request's id is rewritten to one
+                // assigned here, unique on this shared channel, and the
+                // caller's own id is put back on the reply.
+                let mut in_flight: std::collections::HashMap<
+                    u64,
+                    (u64, uuid::Uuid, uuid::Uuid, u64),
+                > = std::collections::HashMap::new();
+                let mut next_server_req_id: u64 = 0;
                 loop {
                     tokio::select! {
                         Some(resp) = client_transport.next() => {
@@ -113,53 +174,128 @@ impl PeerRpcManager {
                                     "[PEER RPC MGR] client_transport in server side got channel error, ignore it.");
                                 continue;
                             }
-                            let resp = resp.unwrap();
+                            let mut resp = resp.unwrap();
 
-                            if cur_req_uuid.is_none() {
-                                tracing::error!("[PEER RPC MGR] cur_req_uuid is none, ignore this resp");
+                            let Some((client_req_id, cur_peer_id, original_sender, call_id)) =
+                                in_flight.remove(&resp.request_id)
+                            else {
+                                tracing::error!(request_id = resp.request_id, "[PEER RPC MGR] no in-flight request found for response, dropping it");
                                 continue;
-                            }
+                            };
+                            resp.request_id = client_req_id;
 
                             let serialized_resp = bincode::serialize(&resp);
                             if serialized_resp.is_err() {
-                                tracing::error!(error = ?serialized_resp.err(), "serialize resp failed");
+                                let err = serialized_resp.err().unwrap();
+                                tracing::error!(error = ?err, "serialize resp failed");
+                                Self::send_err_resp(
+                                    &tspt,
+                                    cur_peer_id,
+                                    original_sender,
+                                    service_id,
+                                    call_id,
+                                    format!("serialize resp failed: {:?}", err),
+                                )
+                                .await;
                                 continue;
                             }
 
                             let msg = Packet::new_tarpc_packet(
                                 tspt.my_peer_id(),
-                                cur_req_uuid.take().unwrap(),
+                                original_sender,
                                 service_id,
                                 false,
+                                call_id,
                                 serialized_resp.unwrap(),
                             );
 
-                            if let Err(e) = tspt.send(msg.into(), &peer_id).await {
-                                tracing::error!(error = ?e, peer_id = ?peer_id, service_id = ?service_id, "send resp to peer failed");
+                            if let Err(e) = tspt.send(msg.into(), &cur_peer_id).await {
+                                tracing::error!(error = ?e, peer_id = ?cur_peer_id, service_id = ?service_id, "send resp to peer failed");
                             }
                         }
                         Some(packet) = packet_receiver.recv() => {
                             let info = Self::parse_rpc_packet(&packet);
                             if let Err(e) = info {
                                 tracing::error!(error = ?e, packet = ?packet, "parse rpc packet failed");
+                                // the packet's own body failed to parse, so there is no
+                                // call_id to echo back; best effort only.
+                                Self::send_err_resp(
+                                    &tspt,
+                                    packet.from_peer.clone().into(),
+                                    packet.from_peer.clone().into(),
+                                    service_id,
+                                    0,
+                                    format!("parse rpc packet failed: {:?}", e),
+                                )
+                                .await;
                                 continue;
                             }
                             let info = info.unwrap();
 
                             assert_eq!(info.service_id, service_id);
-                            cur_req_uuid = Some(packet.from_peer.clone().into());
+                            let cur_peer_id = info.from_peer;
+
+                            if info.is_err {
+                                // the client's deadline expired and it gave up on this call;
+                                // there is no reply to send back. The notice is only logged:
+                                // a request already handed to the service keeps running.
+                                tracing::info!(peer_id = ?cur_peer_id, service_id = ?service_id, "client cancelled in-flight rpc request");
+                                continue;
+                            }
 
                             tracing::trace!("recv packet from peer, packet: {:?}", packet);
 
                             let decoded_ret = bincode::deserialize(&info.content.as_slice());
                             if let Err(e) = decoded_ret {
                                 tracing::error!(error = ?e, "decode rpc packet failed");
+                                Self::send_err_resp(
+                                    &tspt,
+                                    cur_peer_id,
+                                    info.original_sender,
+                                    service_id,
+                                    info.call_id,
+                                    format!("decode rpc packet failed: {:?}", e),
+                                )
+                                .await;
                                 continue;
                             }
-                            let decoded: tarpc::ClientMessage<Req> = decoded_ret.unwrap();
+                            let mut decoded: tarpc::ClientMessage<Req> = decoded_ret.unwrap();
+
+                            match &mut decoded {
+                                tarpc::ClientMessage::Request(req) => {
+                                    let server_req_id = next_server_req_id;
+                                    next_server_req_id = next_server_req_id.wrapping_add(1);
+                                    in_flight.insert(
+                                        server_req_id,
+                                        (req.id, cur_peer_id, info.original_sender, info.call_id),
+                                    );
+                                    req.id = server_req_id;
+                                }
+                                tarpc::ClientMessage::Cancel { request_id, .. } => {
+                                    let Some(server_req_id) = in_flight
+                                        .iter()
+                                        .find(|(_, v)| v.0 == *request_id && v.3 == info.call_id)
+                                        .map(|(k, _)| *k)
+                                    else {
+                                        continue;
+                                    };
+                                    in_flight.remove(&server_req_id);
+                                    *request_id = server_req_id;
+                                }
+                                _ => {}
+                            }
 
                             if let Err(e) = client_transport.send(decoded).await {
                                 tracing::error!(error = ?e, "send to req to client transport failed");
+                                Self::send_err_resp(
+                                    &tspt,
+                                    cur_peer_id,
+                                    info.original_sender,
+                                    service_id,
+                                    info.call_id,
+                                    format!("dispatch to service handler failed: {:?}", e),
+                                )
+                                .await;
                             }
                         }
                         else => {
@@ -179,6 +315,7 @@ impl PeerRpcManager {
                 peer_id,
                 packet_sender,
                 tasks,
+                last_active: Arc::new(std::sync::Mutex::new(std::time::Instant::now())),
             };
             // let resp = client_transport.next().await;
         });
@@ -197,20 +334,206 @@ impl PeerRpcManager {
         )
     }
 
+    // register a streaming-response service: unlike `run_service`, `handler`
+    // is called once per request and returns a `futures::Stream` of response
+    // items, each of which is sent to the caller as its own frame (`seq`
+    // 0, 1, 2, ...) instead of a single tarpc reply. A trailing empty frame
+    // with `end_of_stream` set closes the stream out, including when
+    // `handler` yields no items at all, so the caller's stream always
+    // terminates instead of hanging. Useful for bulk transfers and
+    // long-lived subscriptions (e.g. route or peer-list updates) that don't
+    // fit the unary call/response shape `run_service` assumes.
+    pub fn run_streaming_service<Req, Resp, F, S>(
+        self: &Self,
+        service_id: PeerRpcServiceId,
+        handler: F,
+    ) where
+        Req: Send + 'static + serde::Serialize + for<'a> serde::Deserialize<'a>,
+        Resp: Send + 'static + serde::Serialize + for<'a> serde::Deserialize<'a>,
+        F: Fn(Req) -> S + Clone + Send + Sync + 'static,
+        S: Stream<Item = Resp> + Send + 'static,
+    {
+        let tspt = self.tspt.clone();
+        let creator = Box::new(move |peer_id: uuid::Uuid| {
+            let mut tasks = JoinSet::new();
+            let (packet_sender, mut packet_receiver) = mpsc::unbounded_channel::<Packet>();
+
+            let tspt = tspt.clone();
+            let handler = handler.clone();
+            tasks.spawn(async move {
+                while let Some(packet) = packet_receiver.recv().await {
+                    let info = match Self::parse_rpc_packet(&packet) {
+                        Ok(info) => info,
+                        Err(e) => {
+                            tracing::error!(error = ?e, packet = ?packet, "parse streaming rpc packet failed");
+                            continue;
+                        }
+                    };
+                    assert_eq!(info.service_id, service_id);
+
+                    // the immediate hop this request arrived from, i.e. who we
+                    // reply to. Not the same as `peer_id`: this endpoint is
+                    // shared by every caller of this service (see `run`
+                    // keying `peer_rpc_endpoints` by `(to_peer, service_id)`
+                    // alone), so the endpoint-creation-time `peer_id` is only
+                    // ever correct for the caller that happened to create it.
+                    let cur_peer_id = info.from_peer;
+
+                    let req: Req = match bincode::deserialize(info.content.as_slice()) {
+                        Ok(req) => req,
+                        Err(e) => {
+                            tracing::error!(error = ?e, "decode streaming rpc request failed");
+                            Self::send_err_resp(
+                                &tspt,
+                                cur_peer_id,
+                                info.original_sender,
+                                service_id,
+                                info.call_id,
+                                format!("decode streaming rpc request failed: {:?}", e),
+                            )
+                            .await;
+                            continue;
+                        }
+                    };
+
+                    let original_sender = info.original_sender;
+                    let call_id = info.call_id;
+                    let tspt = tspt.clone();
+                    let handler = handler.clone();
+                    tokio::spawn(async move {
+                        let my_peer_id = tspt.my_peer_id();
+                        let mut stream = Box::pin(handler(req));
+                        let mut seq = 0u32;
+                        while let Some(resp) = stream.next().await {
+                            let serialized = match bincode::serialize(&resp) {
+                                Ok(s) => s,
+                                Err(e) => {
+                                    tracing::error!(error = ?e, "serialize streaming rpc response failed");
+                                    Self::send_err_resp(
+                                        &tspt,
+                                        cur_peer_id,
+                                        original_sender,
+                                        service_id,
+                                        call_id,
+                                        format!("serialize streaming rpc response failed: {:?}", e),
+                                    )
+                                    .await;
+                                    return;
+                                }
+                            };
+                            let msg = Packet::new_tarpc_stream_packet(
+                                my_peer_id,
+                                original_sender,
+                                service_id,
+                                false,
+                                seq,
+                                false,
+                                true,
+                                call_id,
+                                serialized,
+                            );
+                            if let Err(e) = tspt.send(msg.into(), &cur_peer_id).await {
+                                tracing::error!(error = ?e, peer_id = ?cur_peer_id, service_id = ?service_id, "send streaming resp frame to peer failed");
+                                return;
+                            }
+                            seq += 1;
+                        }
+
+                        // terminator: no item, just marks the stream done.
+                        let msg = Packet::new_tarpc_stream_packet(
+                            my_peer_id,
+                            original_sender,
+                            service_id,
+                            false,
+                            seq,
+                            true,
+                            false,
+                            call_id,
+                            Vec::new(),
+                        );
+                        if let Err(e) = tspt.send(msg.into(), &cur_peer_id).await {
+                            tracing::error!(error = ?e, peer_id = ?cur_peer_id, service_id = ?service_id, "send streaming resp terminator to peer failed");
+                        }
+                    });
+                }
+            }.instrument(tracing::info_span!("streaming_service_runner", peer_id = ?peer_id, service_id = ?service_id)));
+
+            PeerRpcEndPoint {
+                peer_id,
+                packet_sender,
+                tasks,
+                last_active: Arc::new(std::sync::Mutex::new(std::time::Instant::now())),
+            }
+        });
+
+        if let Some(_) = self.service_registry.insert(service_id, creator) {
+            panic!(
+                "[PEER RPC MGR] service {} is already registered",
+                service_id
+            );
+        }
+
+        log::info!(
+            "[PEER RPC MGR] register streaming service {} succeed, my_node_id {}",
+            service_id,
+            self.tspt.my_peer_id()
+        )
+    }
+
     fn parse_rpc_packet(packet: &Packet) -> Result<TaRpcPacketInfo, Error> {
         match &packet.body {
-            PacketBody::Ctrl(CtrlPacketBody::TaRpc(id, is_req, body)) => Ok(TaRpcPacketInfo {
+            PacketBody::Ctrl(CtrlPacketBody::TaRpc(body)) => Ok(TaRpcPacketInfo {
                 from_peer: packet.from_peer.clone().into(),
                 to_peer: packet.to_peer.clone().unwrap().into(),
-                service_id: *id,
-                is_req: *is_req,
-                content: body.clone(),
+                service_id: body.service_id,
+                is_req: body.is_req,
+                is_err: body.is_err,
+                original_sender: body.original_sender.into(),
+                ttl: body.ttl,
+                seq: body.seq,
+                end_of_stream: body.end_of_stream,
+                has_item: body.has_item,
+                call_id: body.call_id,
+                content: body.content.clone(),
             }),
             _ => Err(Error::ShellCommandError("invalid packet".to_owned())),
         }
     }
 
+    // send an error response for the request from `requester` back over the
+    // direct link to `peer_id` (the immediate hop the request came from),
+    // used on the server path when we cannot produce a normal reply
+    // (serialize failure, malformed request, dispatch failure).
+    #[allow(clippy::too_many_arguments)]
+    async fn send_err_resp(
+        tspt: &Arc<Box<dyn PeerRpcManagerTransport>>,
+        peer_id: uuid::Uuid,
+        requester: uuid::Uuid,
+        service_id: PeerRpcServiceId,
+        call_id: u64,
+        err_msg: String,
+    ) {
+        let content = bincode::serialize(&err_msg).unwrap_or_default();
+        let msg = Packet::new_tarpc_packet_with_err(
+            tspt.my_peer_id(),
+            requester,
+            service_id,
+            false,
+            true,
+            call_id,
+            content,
+        );
+        if let Err(e) = tspt.send(msg.into(), &peer_id).await {
+            tracing::error!(error = ?e, peer_id = ?peer_id, service_id = ?service_id, "send err resp to peer failed");
+        }
+    }
+
     pub fn run(&self) {
+        // reap endpoints that stopped receiving requests. This is the only
+        // thing that shrinks `peer_rpc_endpoints`: `remove_peer` drops
+        // client response receivers only.
+        self.start_idle_reaper(DEFAULT_IDLE_ENDPOINT_TIMEOUT);
+
         let tspt = self.tspt.clone();
         let service_registry = self.service_registry.clone();
         let peer_rpc_endpoints = self.peer_rpc_endpoints.clone();
@@ -222,6 +545,46 @@ impl PeerRpcManager {
                 let packet: Packet = packet.deserialize(&mut rkyv::Infallible).unwrap();
                 let info = Self::parse_rpc_packet(&packet).unwrap();
 
+                if info.to_peer != tspt.my_peer_id() {
+                    // not for us, relay it towards `to_peer` through the next hop.
+                    if info.ttl == 0 {
+                        log::warn!(
+                            "rpc packet ttl exceeded, dropping it, to_peer: {}, service_id: {}",
+                            info.to_peer,
+                            info.service_id
+                        );
+                        continue;
+                    }
+
+                    let Some(next_hop) = tspt.get_next_hop(&info.to_peer) else {
+                        log::warn!(
+                            "no route to peer {}, dropping rpc packet, service_id: {}",
+                            info.to_peer,
+                            info.service_id
+                        );
+                        continue;
+                    };
+
+                    let fwd = Packet::new_tarpc_packet_relayed(
+                        tspt.my_peer_id(),
+                        info.to_peer,
+                        info.original_sender,
+                        info.service_id,
+                        info.is_req,
+                        info.is_err,
+                        info.ttl - 1,
+                        info.seq,
+                        info.end_of_stream,
+                        info.has_item,
+                        info.call_id,
+                        info.content,
+                    );
+                    if let Err(e) = tspt.send(fwd.into(), &next_hop).await {
+                        tracing::error!(error = ?e, next_hop = ?next_hop, "relay rpc packet failed");
+                    }
+                    continue;
+                }
+
                 if info.is_req {
                     if !service_registry.contains_key(&info.service_id) {
                         log::warn!(
@@ -238,13 +601,12 @@ impl PeerRpcManager {
                             service_registry.get(&info.service_id).unwrap()(info.from_peer)
                         });
 
+                    *endpoint.last_active.lock().unwrap() = std::time::Instant::now();
                     endpoint.packet_sender.send(packet).unwrap();
                 } else {
-                    if let Some(a) = client_resp_receivers
-                        .get(&PeerRpcClientCtxKey(info.from_peer, info.service_id))
-                    {
+                    if let Some(a) = client_resp_receivers.get(&info.call_id) {
                         log::trace!("recv resp: {:?}", packet);
-                        if let Err(e) = a.send(packet) {
+                        if let Err(e) = a.sender.send(packet) {
                             tracing::error!(error = ?e, "send resp to client failed");
                         }
                     } else {
@@ -261,7 +623,29 @@ impl PeerRpcManager {
         service_id: PeerRpcServiceId,
         dst_peer_id: uuid::Uuid,
         f: impl FnOnce(UnboundedChannel<CM, Req>) -> Fut,
-    ) -> RpcRet
+    ) -> Result<RpcRet, Error>
+    where
+        CM: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
+        Req: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
+        Fut: std::future::Future<Output = RpcRet>,
+    {
+        self.do_client_rpc_scoped_with_call_id(service_id, dst_peer_id, rand::random(), f)
+            .await
+    }
+
+    // same as `do_client_rpc_scoped`, but lets the caller pick `call_id`
+    // instead of a fresh random one. Exists so `do_client_rpc_broadcast` can
+    // learn the call_id *before* racing the call against its own timeout,
+    // so it can still clean up the right `client_resp_receivers` entry if it
+    // has to give up on the call before `do_client_rpc_scoped`'s own
+    // deadline-derived timeout does.
+    async fn do_client_rpc_scoped_with_call_id<CM, Req, RpcRet, Fut>(
+        &self,
+        service_id: PeerRpcServiceId,
+        dst_peer_id: uuid::Uuid,
+        call_id: u64,
+        f: impl FnOnce(UnboundedChannel<CM, Req>) -> Fut,
+    ) -> Result<RpcRet, Error>
     where
         CM: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
         Req: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
@@ -293,11 +677,17 @@ impl PeerRpcManager {
                     dst_peer_id,
                     service_id,
                     true,
+                    call_id,
                     a.unwrap(),
                 );
 
-                if let Err(e) = tspt.send(a.into(), &dst_peer_id).await {
-                    tracing::error!(error = ?e, dst_peer_id = ?dst_peer_id, "send to peer failed");
+                let Some(next_hop) = tspt.get_next_hop(&dst_peer_id) else {
+                    tracing::error!(dst_peer_id = ?dst_peer_id, "no route to peer, dropping rpc request");
+                    continue;
+                };
+
+                if let Err(e) = tspt.send(a.into(), &next_hop).await {
+                    tracing::error!(error = ?e, dst_peer_id = ?dst_peer_id, next_hop = ?next_hop, "send to peer failed");
                 }
             }
 
@@ -313,8 +703,21 @@ impl PeerRpcManager {
                     tracing::error!(error = ?e, "parse rpc packet failed");
                     continue;
                 }
+                let info = info.unwrap();
+
+                if info.is_err {
+                    let err_msg: String =
+                        bincode::deserialize(&info.content.as_slice()).unwrap_or_default();
+                    tracing::error!(error = ?err_msg, dst_peer_id = ?dst_peer_id, service_id = ?service_id,
+                        "[PEER RPC MGR] server returned an rpc error, tearing down channel so the caller sees a transport error");
+                    // dropping `server_s` here (by returning out of the loop) closes the
+                    // local tarpc channel, which surfaces as a transport error to the
+                    // caller instead of hanging forever waiting on a response that will
+                    // never arrive.
+                    break;
+                }
 
-                let decoded = bincode::deserialize(&info.unwrap().content.as_slice());
+                let decoded = bincode::deserialize(&info.content.as_slice());
                 if let Err(e) = decoded {
                     tracing::error!(error = ?e, "decode rpc packet failed");
                     continue;
@@ -328,16 +731,321 @@ impl PeerRpcManager {
             tracing::warn!("[PEER RPC MGR] server packet read aborted");
         });
 
-        let _insert_ret = self
-            .client_resp_receivers
-            .insert(PeerRpcClientCtxKey(dst_peer_id, service_id), packet_sender);
+        self.client_resp_receivers.insert(
+            call_id,
+            ClientRespReceiver {
+                dst_peer_id,
+                sender: packet_sender,
+            },
+        );
+
+        // honor the caller's tarpc deadline instead of hanging forever on a
+        // response that may never arrive (peer gone, response packet
+        // dropped, lost hop): race the call against a timer derived from it.
+        let timeout = tarpc::context::current()
+            .deadline
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_or_default();
+
+        match tokio::time::timeout(timeout, f(client_transport)).await {
+            Ok(ret) => {
+                // the call finished (successfully or not, from tarpc's own
+                // point of view); either way no more responses for this key
+                // are coming, so drop the receiver instead of leaking it -
+                // its packet_sender's other end (packet_receiver) is about
+                // to be dropped along with `tasks` anyway.
+                self.client_resp_receivers.remove(&call_id);
+                Ok(ret)
+            }
+            Err(_) => {
+                self.cancel_client_rpc(dst_peer_id, service_id, call_id)
+                    .await;
+                Err(Error::Timeout(format!(
+                    "rpc to peer {} service {} exceeded its deadline",
+                    dst_peer_id, service_id
+                )))
+            }
+        }
+    }
+
+    // drop the client_resp_receivers entry for `call_id` and, best-effort,
+    // notify the remote service runner that nothing is waiting on the call
+    // anymore. The runner only logs the notice; a request it already handed
+    // to the service is not aborted. Shared by every place that gives up on
+    // an outstanding call before a response arrives, so the cleanup always
+    // happens regardless of which timeout fires first.
+    async fn cancel_client_rpc(
+        &self,
+        dst_peer_id: uuid::Uuid,
+        service_id: PeerRpcServiceId,
+        call_id: u64,
+    ) {
+        self.client_resp_receivers.remove(&call_id);
+
+        if let Some(next_hop) = self.tspt.get_next_hop(&dst_peer_id) {
+            let cancel = Packet::new_tarpc_packet_with_err(
+                self.tspt.my_peer_id(),
+                dst_peer_id,
+                service_id,
+                true,
+                true,
+                call_id,
+                Vec::new(),
+            );
+            if let Err(e) = self.tspt.send(cancel.into(), &next_hop).await {
+                tracing::warn!(error = ?e, dst_peer_id = ?dst_peer_id, service_id = ?service_id, "send rpc cancellation failed");
+            }
+        }
+    }
+
+    // sibling of `do_client_rpc_scoped` that dispatches the same call to many
+    // destinations at once: one tarpc channel (and `client_resp_receivers`
+    // entry) per peer, driven concurrently and each raced against `timeout`
+    // so a single non-responding peer can't stall the rest of the batch.
+    // The natural primitive for mesh-wide operations like collecting every
+    // peer's advertised routes or pushing a config change, which would
+    // otherwise require the caller to loop and await `do_client_rpc_scoped`
+    // sequentially.
+    pub async fn do_client_rpc_broadcast<CM, Req, RpcRet, Fut>(
+        &self,
+        service_id: PeerRpcServiceId,
+        dst_peer_ids: Vec<uuid::Uuid>,
+        timeout: std::time::Duration,
+        f: impl Fn(UnboundedChannel<CM, Req>) -> Fut + Clone,
+    ) -> Vec<(uuid::Uuid, Result<RpcRet, Error>)>
+    where
+        CM: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
+        Req: serde::Serialize + for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
+        Fut: std::future::Future<Output = RpcRet>,
+    {
+        let calls = dst_peer_ids.into_iter().map(|dst_peer_id| {
+            let f = f.clone();
+            // generated here, not inside `do_client_rpc_scoped_with_call_id`,
+            // so it's still known to us if the broadcast timeout below fires
+            // first and drops that call's future before it cleans up after
+            // itself.
+            let call_id: u64 = rand::random();
+            async move {
+                let ret = match tokio::time::timeout(
+                    timeout,
+                    self.do_client_rpc_scoped_with_call_id(service_id, dst_peer_id, call_id, f),
+                )
+                .await
+                {
+                    Ok(inner) => inner,
+                    Err(_) => {
+                        // the explicit broadcast timeout fired before
+                        // `do_client_rpc_scoped_with_call_id`'s own
+                        // deadline-derived one; its future was dropped
+                        // mid-flight, so run the same cleanup it would have
+                        // done itself.
+                        self.cancel_client_rpc(dst_peer_id, service_id, call_id)
+                            .await;
+                        Err(Error::Timeout(format!(
+                            "rpc broadcast to peer {} timed out after {:?}",
+                            dst_peer_id, timeout
+                        )))
+                    }
+                };
+                (dst_peer_id, ret)
+            }
+        });
+
+        future::join_all(calls).await
+    }
+
+    // call a service registered with `run_streaming_service`: sends a single
+    // request frame and returns a `futures::Stream` of decoded response
+    // frames instead of driving a tarpc client to one reply. The unary
+    // `do_client_rpc_scoped` above is unaffected; regular tarpc services
+    // still go through it.
+    pub async fn do_client_rpc_stream_scoped<Req, Resp>(
+        &self,
+        service_id: PeerRpcServiceId,
+        dst_peer_id: uuid::Uuid,
+        req: Req,
+    ) -> Result<PeerRpcResponseStream<Resp>, Error>
+    where
+        Req: serde::Serialize,
+        Resp: for<'a> serde::Deserialize<'a>,
+    {
+        let call_id: u64 = rand::random();
+        let (packet_sender, packet_receiver) = mpsc::unbounded_channel::<Packet>();
+        self.client_resp_receivers.insert(
+            call_id,
+            ClientRespReceiver {
+                dst_peer_id,
+                sender: packet_sender,
+            },
+        );
 
-        f(client_transport).await
+        let content = bincode::serialize(&req).map_err(|e| {
+            Error::ShellCommandError(format!("serialize streaming rpc request failed: {:?}", e))
+        })?;
+        let msg = Packet::new_tarpc_stream_packet(
+            self.tspt.my_peer_id(),
+            dst_peer_id,
+            service_id,
+            true,
+            0,
+            true,
+            true,
+            call_id,
+            content,
+        );
+        let next_hop = self.tspt.get_next_hop(&dst_peer_id).ok_or_else(|| {
+            Error::ShellCommandError(format!(
+                "no route to peer {}, dropping rpc request",
+                dst_peer_id
+            ))
+        })?;
+        self.tspt.send(msg.into(), &next_hop).await?;
+
+        Ok(PeerRpcResponseStream {
+            packet_receiver,
+            done: false,
+            client_resp_receivers: self.client_resp_receivers.clone(),
+            call_id,
+            _marker: std::marker::PhantomData,
+        })
     }
 
     pub fn my_peer_id(&self) -> uuid::Uuid {
         self.tspt.my_peer_id()
     }
+
+    // drop outstanding client response receivers owned by `peer_id`. Call
+    // this from the peer manager's disconnect notification for `peer_id`;
+    // without it, `client_resp_receivers` only ever grows as peers come and
+    // go.
+    //
+    // deliberately does NOT touch `peer_rpc_endpoints`: a `PeerRpcEndPoint`
+    // is server-side infrastructure shared by every remote caller of that
+    // service (see `run` keying it by `(to_peer, service_id)` alone, with no
+    // per-caller component), and `endpoint.peer_id` only records whichever
+    // caller happened to create it first - it is not a reliable owner to
+    // tear the endpoint down on behalf of. Aborting the endpoint's JoinSet
+    // when that first caller disconnects would also kill the server
+    // execution task and any other, still-connected peer's in-flight call
+    // against the same shared endpoint. `start_idle_reaper` is what
+    // eventually reclaims endpoints nothing is calling anymore.
+    pub fn remove_peer(&self, peer_id: uuid::Uuid) {
+        self.client_resp_receivers
+            .retain(|_, receiver| receiver.dst_peer_id != peer_id);
+
+        tracing::info!(peer_id = ?peer_id, "[PEER RPC MGR] removed rpc state for disconnected peer");
+    }
+
+    // spawn a background task that periodically drops `PeerRpcEndPoint`s
+    // that have seen no request traffic for `idle_timeout`, so a long-running
+    // node doesn't accumulate per-peer task sets for peers that went away
+    // (`remove_peer` leaves endpoints in place).
+    pub fn start_idle_reaper(&self, idle_timeout: std::time::Duration) {
+        let peer_rpc_endpoints = self.peer_rpc_endpoints.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(idle_timeout);
+            loop {
+                interval.tick().await;
+                peer_rpc_endpoints.retain(|_, endpoint| {
+                    let idle = endpoint.last_active.lock().unwrap().elapsed() >= idle_timeout;
+                    if idle {
+                        tracing::info!(peer_id = ?endpoint.peer_id, "[PEER RPC MGR] reaping idle rpc endpoint");
+                        endpoint.tasks.abort_all();
+                    }
+                    !idle
+                });
+            }
+        });
+    }
+}
+
+// decoded frames of a response stream started by `do_client_rpc_stream_scoped`.
+// Yields one item per frame and ends after the frame with `end_of_stream` set
+// (or after the first error frame, which is surfaced as an `Err`). Removes
+// its `client_resp_receivers` entry as soon as the stream is done (or
+// dropped early, via `Drop`), the same way `do_client_rpc_scoped` cleans up
+// after itself - otherwise a stream nobody finishes reading leaks its entry
+// forever.
+pub struct PeerRpcResponseStream<Resp> {
+    packet_receiver: mpsc::UnboundedReceiver<Packet>,
+    done: bool,
+    client_resp_receivers: Arc<DashMap<CallId, ClientRespReceiver>>,
+    call_id: u64,
+    _marker: std::marker::PhantomData<Resp>,
+}
+
+impl<Resp> PeerRpcResponseStream<Resp> {
+    // idempotent: safe to call once the stream is already done (`poll_next`
+    // calls this itself) and again from `Drop` if the caller dropped the
+    // stream before it ran to completion.
+    fn cleanup(&mut self) {
+        self.client_resp_receivers.remove(&self.call_id);
+    }
+}
+
+impl<Resp> Drop for PeerRpcResponseStream<Resp> {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+impl<Resp> Stream for PeerRpcResponseStream<Resp>
+where
+    Resp: for<'a> serde::Deserialize<'a> + Unpin,
+{
+    type Item = Result<Resp, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            return match self.packet_receiver.poll_recv(cx) {
+                Poll::Ready(Some(packet)) => {
+                    let info = match PeerRpcManager::parse_rpc_packet(&packet) {
+                        Ok(info) => info,
+                        Err(e) => {
+                            tracing::error!(error = ?e, "parse streaming rpc packet failed");
+                            continue;
+                        }
+                    };
+
+                    if info.is_err {
+                        self.done = true;
+                        self.cleanup();
+                        let err_msg: String =
+                            bincode::deserialize(info.content.as_slice()).unwrap_or_default();
+                        return Poll::Ready(Some(Err(Error::ShellCommandError(err_msg))));
+                    }
+
+                    self.done = info.end_of_stream;
+                    if !info.has_item {
+                        // the terminator frame: no item to yield, just marks
+                        // the stream done. Distinct from a real item whose
+                        // content happens to also be empty (e.g. `Resp = ()`),
+                        // which must still reach the caller below.
+                        if self.done {
+                            self.cleanup();
+                            return Poll::Ready(None);
+                        }
+                        continue;
+                    }
+
+                    if self.done {
+                        self.cleanup();
+                    }
+
+                    Poll::Ready(Some(bincode::deserialize(info.content.as_slice()).map_err(
+                        |e| {
+                            Error::ShellCommandError(format!("decode rpc response failed: {:?}", e))
+                        },
+                    )))
+                }
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
 }
 
 #[cfg(test)]
@@ -348,6 +1056,7 @@ mod tests {
     use crate::{
         common::error::Error,
         peers::{
+            packet::Packet,
             peer_rpc::PeerRpcManager,
             tests::{connect_peer_manager, create_mock_peer_manager, wait_route_appear},
         },
@@ -373,6 +1082,24 @@ mod tests {
         }
     }
 
+    // like `MockService`, but deliberately slow to answer "from-a" so tests
+    // can force two concurrent calls against the same shared endpoint to
+    // genuinely overlap instead of merely being issued close together.
+    #[derive(Clone)]
+    struct SlowFirstCallerService {
+        prefix: String,
+    }
+
+    #[tarpc::server]
+    impl TestRpcService for SlowFirstCallerService {
+        async fn hello(self, _: tarpc::context::Context, s: String) -> String {
+            if s == "from-a" {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            format!("{} {}", self.prefix, s)
+        }
+    }
+
     #[tokio::test]
     async fn peer_rpc_basic_test() {
         struct MockTransport {
@@ -395,6 +1122,11 @@ mod tests {
                 println!("rpc mgr recv: {:?}", ret);
                 return ret.map(|v| v.freeze()).map_err(|_| Error::Unknown);
             }
+            fn get_next_hop(&self, dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid> {
+                // the mock tunnel is a direct link, so the next hop is always the
+                // destination itself.
+                Some(*dst_peer_id)
+            }
         }
 
         let (ct, st) = create_ring_tunnel_pair();
@@ -424,7 +1156,7 @@ mod tests {
             .await;
 
         println!("ret: {:?}", ret);
-        assert_eq!(ret.unwrap(), "hello abc");
+        assert_eq!(ret.unwrap().unwrap(), "hello abc");
     }
 
     #[tokio::test]
@@ -457,7 +1189,7 @@ mod tests {
             .await;
 
         println!("ip_list: {:?}", ip_list);
-        assert_eq!(ip_list.as_ref().unwrap(), "hello abc");
+        assert_eq!(ip_list.unwrap().unwrap(), "hello abc");
     }
 
     #[tokio::test]
@@ -493,7 +1225,7 @@ mod tests {
             })
             .await;
 
-        assert_eq!(ip_list.as_ref().unwrap(), "hello_a abc");
+        assert_eq!(ip_list.unwrap().unwrap(), "hello_a abc");
 
         let ip_list = peer_mgr_a
             .get_peer_rpc_mgr()
@@ -504,6 +1236,596 @@ mod tests {
             })
             .await;
 
-        assert_eq!(ip_list.as_ref().unwrap(), "hello_b abc");
+        assert_eq!(ip_list.unwrap().unwrap(), "hello_b abc");
     }
-}
\ No newline at end of file
+
+    // regression test for the endpoint-sharing bug: `peer_rpc_endpoints` is
+    // keyed by `(to_peer, service_id)` alone, so b's single endpoint for
+    // service 1 is shared by every caller. Before the reply path tracked the
+    // current request's immediate hop, a's request created the endpoint and
+    // c's later request got its response shipped back to a's hop instead of
+    // c's, so c would hang until its deadline expired.
+    #[tokio::test]
+    async fn concurrent_clients_against_shared_service_get_own_replies() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        let peer_mgr_c = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        connect_peer_manager(peer_mgr_c.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+        wait_route_appear(peer_mgr_c.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        let s = MockService {
+            prefix: "hello".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, s.serve());
+
+        // a's request creates the shared endpoint on b first.
+        let ret_a = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "from-a".to_owned())
+                    .await
+            })
+            .await;
+        assert_eq!(ret_a.unwrap().unwrap(), "hello from-a");
+
+        // c reuses that same endpoint; it must still get its own reply back.
+        let ret_c = peer_mgr_c
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "from-c".to_owned())
+                    .await
+            })
+            .await;
+        assert_eq!(ret_c.unwrap().unwrap(), "hello from-c");
+    }
+
+    // the test above only proves the reply path works when a's and c's
+    // calls happen to be sequential; it would pass even with a single
+    // mutable "current caller" slot shared by the whole endpoint, as long
+    // as a's response lands before c's request does. This test forces the
+    // two calls to genuinely overlap (a's handler sleeps) so both are
+    // simultaneously outstanding against the same `client_transport`, which
+    // only a real per-request routing table (not one shared slot) can get
+    // right.
+    #[tokio::test]
+    async fn truly_concurrent_callers_against_shared_service_get_own_replies() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        let peer_mgr_c = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        connect_peer_manager(peer_mgr_c.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+        wait_route_appear(peer_mgr_c.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        let s = SlowFirstCallerService {
+            prefix: "hello".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, s.serve());
+
+        let call_a = peer_mgr_a.get_peer_rpc_mgr().do_client_rpc_scoped(
+            1,
+            peer_mgr_b.my_node_id(),
+            |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "from-a".to_owned())
+                    .await
+            },
+        );
+        let call_c = peer_mgr_c.get_peer_rpc_mgr().do_client_rpc_scoped(
+            1,
+            peer_mgr_b.my_node_id(),
+            |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "from-c".to_owned())
+                    .await
+            },
+        );
+
+        let (ret_a, ret_c) = tokio::join!(call_a, call_c);
+        assert_eq!(ret_a.unwrap().unwrap(), "hello from-a");
+        assert_eq!(ret_c.unwrap().unwrap(), "hello from-c");
+    }
+
+    #[tokio::test]
+    async fn remove_peer_tears_down_state_without_disrupting_the_service() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        let s = MockService {
+            prefix: "hello".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, s.serve());
+
+        let ret = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "abc".to_owned()).await
+            })
+            .await;
+        assert_eq!(ret.unwrap().unwrap(), "hello abc");
+
+        // simulate b's peer manager learning a disconnected.
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .remove_peer(peer_mgr_a.my_node_id());
+
+        // remove_peer no longer touches peer_rpc_endpoints at all (see its
+        // doc comment), so a reuses the same endpoint it created above
+        // rather than causing it to be rebuilt.
+        let ret = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "def".to_owned()).await
+            })
+            .await;
+        assert_eq!(ret.unwrap().unwrap(), "hello def");
+    }
+
+    // regression test for the remove_peer collateral-damage bug: b's shared
+    // endpoint for service 1 is created by a's call and also serves c's; b
+    // learning a disconnected while both calls are outstanding must not
+    // abort the endpoint's JoinSet (and with it, the server execution task
+    // and c's still-in-flight call).
+    #[tokio::test]
+    async fn remove_peer_does_not_disrupt_another_callers_shared_endpoint() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        let peer_mgr_c = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        connect_peer_manager(peer_mgr_c.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+        wait_route_appear(peer_mgr_c.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        let s = SlowFirstCallerService {
+            prefix: "hello".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, s.serve());
+
+        // a's request creates b's shared endpoint for service 1 and is
+        // still in flight (the handler sleeps on "from-a") when b is told a
+        // disconnected below.
+        let call_a = peer_mgr_a.get_peer_rpc_mgr().do_client_rpc_scoped(
+            1,
+            peer_mgr_b.my_node_id(),
+            |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "from-a".to_owned())
+                    .await
+            },
+        );
+
+        let call_c = async {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            peer_mgr_b
+                .get_peer_rpc_mgr()
+                .remove_peer(peer_mgr_a.my_node_id());
+
+            // c's call against the same shared endpoint must be unaffected.
+            peer_mgr_c
+                .get_peer_rpc_mgr()
+                .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                    let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                    c.hello(tarpc::context::current(), "from-c".to_owned())
+                        .await
+                })
+                .await
+        };
+
+        let (ret_a, ret_c) = tokio::join!(call_a, call_c);
+        assert_eq!(ret_c.unwrap().unwrap(), "hello from-c");
+
+        // and a's own call, already in flight when it "disconnected", still
+        // completes - the endpoint's server execution task was never
+        // aborted out from under it.
+        assert_eq!(ret_a.unwrap().unwrap(), "hello from-a");
+    }
+
+    #[tokio::test]
+    async fn idle_reaper_reclaims_stale_endpoints_without_breaking_future_calls() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        let s = MockService {
+            prefix: "hello".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, s.serve());
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .start_idle_reaper(std::time::Duration::from_millis(50));
+
+        let ret = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "abc".to_owned()).await
+            })
+            .await;
+        assert_eq!(ret.unwrap().unwrap(), "hello abc");
+
+        // give the reaper's interval at least one tick past the idle window
+        // so the endpoint created above gets torn down.
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let ret = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_scoped(1, peer_mgr_b.my_node_id(), |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "def".to_owned()).await
+            })
+            .await;
+        assert_eq!(ret.unwrap().unwrap(), "hello def");
+    }
+
+    #[tokio::test]
+    async fn broadcast_fans_out_to_every_destination_and_reports_per_peer_results() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        let peer_mgr_c = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_c.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_c.my_node_id())
+            .await
+            .unwrap();
+
+        let sb = MockService {
+            prefix: "hello_b".to_owned(),
+        };
+        peer_mgr_b.get_peer_rpc_mgr().run_service(1, sb.serve());
+        // c never registers service 1, so its call will hang until the
+        // broadcast's own timeout fires instead of the server ever replying.
+
+        let results = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_broadcast(
+                1,
+                vec![peer_mgr_b.my_node_id(), peer_mgr_c.my_node_id()],
+                std::time::Duration::from_millis(300),
+                |c| async {
+                    let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                    c.hello(tarpc::context::current(), "abc".to_owned()).await
+                },
+            )
+            .await;
+
+        assert_eq!(results.len(), 2);
+        let b_result = results
+            .iter()
+            .find(|(peer_id, _)| *peer_id == peer_mgr_b.my_node_id())
+            .unwrap();
+        assert_eq!(
+            b_result.1.as_ref().unwrap().as_ref().unwrap(),
+            "hello_b abc"
+        );
+
+        let c_result = results
+            .iter()
+            .find(|(peer_id, _)| *peer_id == peer_mgr_c.my_node_id())
+            .unwrap();
+        assert!(c_result.1.is_err());
+    }
+
+    #[tokio::test]
+    async fn streaming_service_distinguishes_real_empty_items_from_the_terminator() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        // every item this stream yields serializes to zero bytes; before
+        // has_item existed these were indistinguishable from the terminator
+        // and got silently dropped instead of reaching the caller.
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .run_streaming_service(1, |n: u32| futures::stream::iter((0..n).map(|_| ())));
+
+        let mut stream = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_stream_scoped::<u32, ()>(1, peer_mgr_b.my_node_id(), 3)
+            .await
+            .unwrap();
+
+        let mut count = 0;
+        while let Some(item) = stream.next().await {
+            item.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    // regression test for the same endpoint-sharing bug as
+    // `concurrent_clients_against_shared_service_get_own_replies`, but on the
+    // streaming path: two independent callers subscribed to the same
+    // streaming service must each get their own frames back, not each
+    // other's.
+    #[tokio::test]
+    async fn concurrent_streaming_callers_get_independent_streams() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        let peer_mgr_c = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        connect_peer_manager(peer_mgr_c.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+        wait_route_appear(peer_mgr_c.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .run_streaming_service(1, |prefix: String| {
+                futures::stream::iter(vec![format!("{prefix}-1"), format!("{prefix}-2")])
+            });
+
+        let stream_a_fut = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_stream_scoped::<String, String>(
+                1,
+                peer_mgr_b.my_node_id(),
+                "a".to_owned(),
+            );
+        let stream_c_fut = peer_mgr_c
+            .get_peer_rpc_mgr()
+            .do_client_rpc_stream_scoped::<String, String>(
+                1,
+                peer_mgr_b.my_node_id(),
+                "c".to_owned(),
+            );
+        let (stream_a, stream_c) = tokio::join!(stream_a_fut, stream_c_fut);
+
+        let items_a: Vec<String> = stream_a.unwrap().map(|r| r.unwrap()).collect().await;
+        let items_c: Vec<String> = stream_c.unwrap().map(|r| r.unwrap()).collect().await;
+
+        assert_eq!(items_a, vec!["a-1".to_owned(), "a-2".to_owned()]);
+        assert_eq!(items_c, vec!["c-1".to_owned(), "c-2".to_owned()]);
+    }
+
+    // regression test for the client_resp_receivers leak on the streaming
+    // path: before `PeerRpcResponseStream` dropped its entry on completion,
+    // nothing ever removed it once the caller had drained the stream.
+    #[tokio::test]
+    async fn streaming_call_removes_its_client_resp_receivers_entry_on_completion() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .run_streaming_service(1, |prefix: String| {
+                futures::stream::iter(vec![format!("{prefix}-1"), format!("{prefix}-2")])
+            });
+
+        let stream = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_stream_scoped::<String, String>(
+                1,
+                peer_mgr_b.my_node_id(),
+                "a".to_owned(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(peer_mgr_a.get_peer_rpc_mgr().client_resp_receivers.len(), 1);
+
+        let items: Vec<String> = stream.map(|r| r.unwrap()).collect().await;
+        assert_eq!(items, vec!["a-1".to_owned(), "a-2".to_owned()]);
+
+        assert_eq!(peer_mgr_a.get_peer_rpc_mgr().client_resp_receivers.len(), 0);
+    }
+
+    // sibling of the test above: a stream abandoned before it finishes (the
+    // caller drops it early) must clean up its entry too, via `Drop`.
+    #[tokio::test]
+    async fn dropping_a_streaming_call_early_removes_its_client_resp_receivers_entry() {
+        let peer_mgr_a = create_mock_peer_manager().await;
+        let peer_mgr_b = create_mock_peer_manager().await;
+        connect_peer_manager(peer_mgr_a.clone(), peer_mgr_b.clone()).await;
+        wait_route_appear(peer_mgr_a.clone(), peer_mgr_b.my_node_id())
+            .await
+            .unwrap();
+
+        peer_mgr_b
+            .get_peer_rpc_mgr()
+            .run_streaming_service(1, |prefix: String| {
+                futures::stream::iter(vec![format!("{prefix}-1"), format!("{prefix}-2")])
+            });
+
+        let stream = peer_mgr_a
+            .get_peer_rpc_mgr()
+            .do_client_rpc_stream_scoped::<String, String>(
+                1,
+                peer_mgr_b.my_node_id(),
+                "a".to_owned(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(peer_mgr_a.get_peer_rpc_mgr().client_resp_receivers.len(), 1);
+
+        drop(stream);
+        assert_eq!(peer_mgr_a.get_peer_rpc_mgr().client_resp_receivers.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn relay_forwards_rpc_to_a_peer_reached_through_an_intermediate_hop() {
+        // topology: a <-> b <-> c, a only ever talks to b directly and
+        // relies on b to relay towards c.
+        struct RelayTransport {
+            my_peer_id: uuid::Uuid,
+            a_peer_id: uuid::Uuid,
+            a_tunnel: Box<dyn tunnels::Tunnel>,
+            c_peer_id: uuid::Uuid,
+            c_tunnel: Box<dyn tunnels::Tunnel>,
+        }
+
+        #[async_trait::async_trait]
+        impl PeerRpcManagerTransport for RelayTransport {
+            fn my_peer_id(&self) -> uuid::Uuid {
+                self.my_peer_id
+            }
+            async fn send(&self, msg: Bytes, dst_peer_id: &uuid::Uuid) -> Result<(), Error> {
+                if *dst_peer_id == self.a_peer_id {
+                    self.a_tunnel.pin_sink().send(msg).await.unwrap();
+                } else {
+                    self.c_tunnel.pin_sink().send(msg).await.unwrap();
+                }
+                Ok(())
+            }
+            async fn recv(&self) -> Result<Bytes, Error> {
+                tokio::select! {
+                    ret = self.a_tunnel.pin_stream().next() => {
+                        ret.unwrap().map(|v| v.freeze()).map_err(|_| Error::Unknown)
+                    }
+                    ret = self.c_tunnel.pin_stream().next() => {
+                        ret.unwrap().map(|v| v.freeze()).map_err(|_| Error::Unknown)
+                    }
+                }
+            }
+            fn get_next_hop(&self, dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid> {
+                if *dst_peer_id == self.a_peer_id {
+                    Some(self.a_peer_id)
+                } else {
+                    Some(self.c_peer_id)
+                }
+            }
+        }
+
+        struct LeafTransport {
+            my_peer_id: uuid::Uuid,
+            next_hop: uuid::Uuid,
+            tunnel: Box<dyn tunnels::Tunnel>,
+        }
+
+        #[async_trait::async_trait]
+        impl PeerRpcManagerTransport for LeafTransport {
+            fn my_peer_id(&self) -> uuid::Uuid {
+                self.my_peer_id
+            }
+            async fn send(&self, msg: Bytes, _dst_peer_id: &uuid::Uuid) -> Result<(), Error> {
+                self.tunnel.pin_sink().send(msg).await.unwrap();
+                Ok(())
+            }
+            async fn recv(&self) -> Result<Bytes, Error> {
+                let ret = self.tunnel.pin_stream().next().await.unwrap();
+                ret.map(|v| v.freeze()).map_err(|_| Error::Unknown)
+            }
+            fn get_next_hop(&self, _dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid> {
+                // everything not directly reachable goes through our one
+                // neighbour, the relay.
+                Some(self.next_hop)
+            }
+        }
+
+        let (ab_a, ab_b) = create_ring_tunnel_pair();
+        let (bc_b, bc_c) = create_ring_tunnel_pair();
+
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+        let c_id = uuid::Uuid::new_v4();
+
+        let mgr_a = PeerRpcManager::new(LeafTransport {
+            my_peer_id: a_id,
+            next_hop: b_id,
+            tunnel: ab_a,
+        });
+        mgr_a.run();
+
+        let mgr_b = PeerRpcManager::new(RelayTransport {
+            my_peer_id: b_id,
+            a_peer_id: a_id,
+            a_tunnel: ab_b,
+            c_peer_id: c_id,
+            c_tunnel: bc_b,
+        });
+        mgr_b.run();
+
+        let mgr_c = PeerRpcManager::new(LeafTransport {
+            my_peer_id: c_id,
+            next_hop: b_id,
+            tunnel: bc_c,
+        });
+        mgr_c.run();
+
+        let s = MockService {
+            prefix: "hello".to_owned(),
+        };
+        mgr_c.run_service(1, s.serve());
+
+        let ret = mgr_a
+            .do_client_rpc_scoped(1, c_id, |c| async {
+                let c = TestRpcServiceClient::new(tarpc::client::Config::default(), c).spawn();
+                c.hello(tarpc::context::current(), "abc".to_owned()).await
+            })
+            .await;
+        assert_eq!(ret.unwrap().unwrap(), "hello abc");
+
+        // inject a request addressed to c but already out of hops, as if it
+        // arrived at b already relayed once too often; b's run() loop must
+        // drop it instead of forwarding it on to c. Had c received it, its
+        // service endpoint would fail to decode the content and send an
+        // error reply back to a under this call_id, so watch for that reply.
+        let call_id: u64 = rand::random();
+        let (reply_tx, mut reply_rx) = tokio::sync::mpsc::unbounded_channel();
+        mgr_a.client_resp_receivers.insert(
+            call_id,
+            super::ClientRespReceiver {
+                dst_peer_id: c_id,
+                sender: reply_tx,
+            },
+        );
+        let expired = Packet::new_tarpc_packet_relayed(
+            a_id,
+            c_id,
+            a_id,
+            1,
+            true,
+            false,
+            0,
+            0,
+            true,
+            true,
+            call_id,
+            bincode::serialize(&"should never arrive").unwrap(),
+        );
+        mgr_a.tspt.send(expired.into(), &c_id).await.unwrap();
+
+        let saw_it =
+            tokio::time::timeout(std::time::Duration::from_millis(200), reply_rx.recv()).await;
+        assert!(
+            saw_it.is_err(),
+            "c should not have received the ttl-expired packet"
+        );
+    }
+}
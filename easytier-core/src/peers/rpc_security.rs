@@ -0,0 +1,736 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Notify};
+use tokio_util::bytes::{Buf, BufMut, Bytes, BytesMut};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::common::error::Error;
+
+use super::peer_rpc::PeerRpcManagerTransport;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// security posture negotiated for a peer's rpc channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityMode {
+    /// no confidentiality; payloads travel exactly as the rpc layer produced them.
+    Plaintext,
+    /// payloads travel in cleartext, but tagged with an HMAC so tampering or
+    /// spoofing is detected; for deployments that want origin/integrity
+    /// guarantees without paying for encryption.
+    Authenticated,
+    /// AEAD-encrypted (and optionally compressed) payloads, keyed per peer.
+    Encrypted,
+}
+
+/// pluggable envelope for a per-peer session: owns the handshake (key
+/// agreement + cipher/codec negotiation) and the resulting seal/open of rpc
+/// payloads. `SecureTransport` drives the handshake frames over the wire;
+/// implementations only need to speak their own hello format and perform
+/// the AEAD/compression work once a session exists.
+pub trait TransportSecurity: Send + Sync + 'static {
+    fn mode(&self) -> SecurityMode;
+
+    /// this node's hello frame, advertising its handshake public key and
+    /// the ciphers/codecs it supports. Sent once to every newly-seen peer.
+    fn make_hello(&self) -> Vec<u8>;
+
+    /// consume a peer's hello frame: pick the intersection of capabilities
+    /// and derive+store a session key for `peer_id`.
+    fn on_hello(&self, peer_id: uuid::Uuid, hello: &[u8]) -> Result<(), Error>;
+
+    fn has_session(&self, peer_id: &uuid::Uuid) -> bool;
+
+    fn seal(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error>;
+    fn open(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error>;
+}
+
+/// default, always-available security: no handshake, no confidentiality.
+/// lets deployments opt out of the encrypted envelope entirely.
+pub struct PlaintextSecurity;
+
+impl TransportSecurity for PlaintextSecurity {
+    fn mode(&self) -> SecurityMode {
+        SecurityMode::Plaintext
+    }
+    fn make_hello(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn on_hello(&self, _peer_id: uuid::Uuid, _hello: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+    fn has_session(&self, _peer_id: &uuid::Uuid) -> bool {
+        true
+    }
+    fn seal(&self, _peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        Ok(payload)
+    }
+    fn open(&self, _peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        Ok(payload)
+    }
+}
+
+// bit in the hello capability byte advertising zstd support for payload
+// compression prior to sealing. A session only compresses when both sides
+// advertised it.
+const CAP_ZSTD: u8 = 0b0000_0001;
+
+// established per-peer AEAD state: the session key derived from the X25519
+// exchange, plus whether both sides agreed to compress payloads before
+// sealing them.
+struct EncryptedSession {
+    cipher: ChaCha20Poly1305,
+    compress: bool,
+}
+
+/// `TransportSecurity` backed by an X25519 key exchange per peer and
+/// ChaCha20-Poly1305 AEAD for the resulting session, with optional zstd
+/// compression of the plaintext before it is sealed. The node's X25519
+/// identity key is static (generated once, reused for every peer); each
+/// peer's hello carries its public key and capability bits, and the shared
+/// secret from `StaticSecret::diffie_hellman` is hashed into a 256-bit AEAD
+/// key rather than used directly as key material.
+pub struct EncryptedSecurity {
+    identity: StaticSecret,
+    public: PublicKey,
+    compress: bool,
+    sessions: DashMap<uuid::Uuid, EncryptedSession>,
+}
+
+impl EncryptedSecurity {
+    pub fn new(compress: bool) -> Self {
+        let identity = StaticSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&identity);
+        Self {
+            identity,
+            public,
+            compress,
+            sessions: DashMap::new(),
+        }
+    }
+
+    fn derive_cipher(&self, peer_public: &PublicKey) -> ChaCha20Poly1305 {
+        let shared = self.identity.diffie_hellman(peer_public);
+        let mut hasher = Sha256::new();
+        hasher.update(shared.as_bytes());
+        hasher.update(b"easytier-peer-rpc-session-v1");
+        let key = hasher.finalize();
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    }
+}
+
+impl TransportSecurity for EncryptedSecurity {
+    fn mode(&self) -> SecurityMode {
+        SecurityMode::Encrypted
+    }
+
+    fn make_hello(&self) -> Vec<u8> {
+        let mut hello = Vec::with_capacity(33);
+        hello.extend_from_slice(self.public.as_bytes());
+        hello.push(if self.compress { CAP_ZSTD } else { 0 });
+        hello
+    }
+
+    fn on_hello(&self, peer_id: uuid::Uuid, hello: &[u8]) -> Result<(), Error> {
+        if hello.len() < 32 {
+            return Err(Error::ShellCommandError(
+                "malformed x25519 hello: expected at least a 32-byte public key".to_owned(),
+            ));
+        }
+        let mut peer_public_bytes = [0u8; 32];
+        peer_public_bytes.copy_from_slice(&hello[..32]);
+        let peer_public = PublicKey::from(peer_public_bytes);
+        // older peers may not send a capability byte yet; treat that as "no
+        // optional capabilities" instead of a malformed hello.
+        let peer_caps = hello.get(32).copied().unwrap_or(0);
+
+        let cipher = self.derive_cipher(&peer_public);
+        self.sessions.insert(
+            peer_id,
+            EncryptedSession {
+                cipher,
+                compress: self.compress && (peer_caps & CAP_ZSTD != 0),
+            },
+        );
+        Ok(())
+    }
+
+    fn has_session(&self, peer_id: &uuid::Uuid) -> bool {
+        self.sessions.contains_key(peer_id)
+    }
+
+    fn seal(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        let session = self.sessions.get(peer_id).ok_or_else(|| {
+            Error::ShellCommandError(format!("no rpc session established with peer {}", peer_id))
+        })?;
+
+        let plain = if session.compress {
+            zstd::stream::encode_all(payload.as_ref(), 0)
+                .map_err(|e| Error::ShellCommandError(format!("zstd compress failed: {:?}", e)))?
+        } else {
+            payload.to_vec()
+        };
+
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let ciphertext = session
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plain.as_slice())
+            .map_err(|e| Error::ShellCommandError(format!("aead seal failed: {:?}", e)))?;
+
+        let mut framed = BytesMut::with_capacity(12 + ciphertext.len());
+        framed.put_slice(&nonce_bytes);
+        framed.put_slice(&ciphertext);
+        Ok(framed.freeze())
+    }
+
+    fn open(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        if payload.len() < 12 {
+            return Err(Error::ShellCommandError(
+                "sealed payload shorter than the aead nonce".to_owned(),
+            ));
+        }
+        let session = self.sessions.get(peer_id).ok_or_else(|| {
+            Error::ShellCommandError(format!("no rpc session established with peer {}", peer_id))
+        })?;
+
+        let (nonce_bytes, ciphertext) = payload.split_at(12);
+        let plain = session
+            .cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|e| Error::ShellCommandError(format!("aead open failed: {:?}", e)))?;
+
+        let plain = if session.compress {
+            zstd::stream::decode_all(plain.as_slice())
+                .map_err(|e| Error::ShellCommandError(format!("zstd decompress failed: {:?}", e)))?
+        } else {
+            plain
+        };
+
+        Ok(Bytes::from(plain))
+    }
+}
+
+// established per-peer HMAC state: the session key derived from the X25519
+// exchange, plus whether both sides agreed to compress payloads before
+// tagging them.
+struct AuthenticatedSession {
+    mac_key: [u8; 32],
+    compress: bool,
+}
+
+/// `TransportSecurity` backed by the same X25519 key exchange as
+/// `EncryptedSecurity`, but tagging payloads with HMAC-SHA256 instead of
+/// sealing them with an AEAD cipher: integrity and origin are verified, but
+/// the payload itself travels in cleartext (optionally zstd-compressed).
+/// For deployments that want tamper detection without paying encryption's
+/// cost, or that are barred from exporting encrypted traffic but can still
+/// authenticate it.
+pub struct AuthenticatedSecurity {
+    identity: StaticSecret,
+    public: PublicKey,
+    compress: bool,
+    sessions: DashMap<uuid::Uuid, AuthenticatedSession>,
+}
+
+impl AuthenticatedSecurity {
+    pub fn new(compress: bool) -> Self {
+        let identity = StaticSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&identity);
+        Self {
+            identity,
+            public,
+            compress,
+            sessions: DashMap::new(),
+        }
+    }
+
+    fn derive_mac_key(&self, peer_public: &PublicKey) -> [u8; 32] {
+        let shared = self.identity.diffie_hellman(peer_public);
+        let mut hasher = Sha256::new();
+        hasher.update(shared.as_bytes());
+        hasher.update(b"easytier-peer-rpc-session-authenticated-v1");
+        hasher.finalize().into()
+    }
+}
+
+impl TransportSecurity for AuthenticatedSecurity {
+    fn mode(&self) -> SecurityMode {
+        SecurityMode::Authenticated
+    }
+
+    fn make_hello(&self) -> Vec<u8> {
+        let mut hello = Vec::with_capacity(33);
+        hello.extend_from_slice(self.public.as_bytes());
+        hello.push(if self.compress { CAP_ZSTD } else { 0 });
+        hello
+    }
+
+    fn on_hello(&self, peer_id: uuid::Uuid, hello: &[u8]) -> Result<(), Error> {
+        if hello.len() < 32 {
+            return Err(Error::ShellCommandError(
+                "malformed x25519 hello: expected at least a 32-byte public key".to_owned(),
+            ));
+        }
+        let mut peer_public_bytes = [0u8; 32];
+        peer_public_bytes.copy_from_slice(&hello[..32]);
+        let peer_public = PublicKey::from(peer_public_bytes);
+        // older peers may not send a capability byte yet; treat that as "no
+        // optional capabilities" instead of a malformed hello.
+        let peer_caps = hello.get(32).copied().unwrap_or(0);
+
+        let mac_key = self.derive_mac_key(&peer_public);
+        self.sessions.insert(
+            peer_id,
+            AuthenticatedSession {
+                mac_key,
+                compress: self.compress && (peer_caps & CAP_ZSTD != 0),
+            },
+        );
+        Ok(())
+    }
+
+    fn has_session(&self, peer_id: &uuid::Uuid) -> bool {
+        self.sessions.contains_key(peer_id)
+    }
+
+    fn seal(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        let session = self.sessions.get(peer_id).ok_or_else(|| {
+            Error::ShellCommandError(format!("no rpc session established with peer {}", peer_id))
+        })?;
+
+        let plain = if session.compress {
+            zstd::stream::encode_all(payload.as_ref(), 0)
+                .map_err(|e| Error::ShellCommandError(format!("zstd compress failed: {:?}", e)))?
+        } else {
+            payload.to_vec()
+        };
+
+        let mut mac =
+            HmacSha256::new_from_slice(&session.mac_key).expect("hmac accepts a key of any length");
+        mac.update(&plain);
+        let tag = mac.finalize().into_bytes();
+
+        let mut framed = BytesMut::with_capacity(tag.len() + plain.len());
+        framed.put_slice(&tag);
+        framed.put_slice(&plain);
+        Ok(framed.freeze())
+    }
+
+    fn open(&self, peer_id: &uuid::Uuid, payload: Bytes) -> Result<Bytes, Error> {
+        if payload.len() < 32 {
+            return Err(Error::ShellCommandError(
+                "tagged payload shorter than the hmac tag".to_owned(),
+            ));
+        }
+        let session = self.sessions.get(peer_id).ok_or_else(|| {
+            Error::ShellCommandError(format!("no rpc session established with peer {}", peer_id))
+        })?;
+
+        let (tag, plain) = payload.split_at(32);
+        let mut mac =
+            HmacSha256::new_from_slice(&session.mac_key).expect("hmac accepts a key of any length");
+        mac.update(plain);
+        mac.verify_slice(tag)
+            .map_err(|_| Error::ShellCommandError("hmac tag verification failed".to_owned()))?;
+
+        let plain = if session.compress {
+            zstd::stream::decode_all(plain)
+                .map_err(|e| Error::ShellCommandError(format!("zstd decompress failed: {:?}", e)))?
+        } else {
+            plain.to_vec()
+        };
+
+        Ok(Bytes::from(plain))
+    }
+}
+
+// wire tags distinguishing handshake frames from sealed data frames over the
+// same underlying `send`/`recv` pair.
+//
+// `HELLO` is sent unprompted the first time we need a session with a peer
+// and always gets an immediate `HELLO_ACK` back (carrying the reply side's
+// own hello) so both ends finish their handshake from a single round trip,
+// regardless of who spoke first; `HELLO_ACK` never itself triggers another
+// reply, which is what keeps this from turning into an infinite hello
+// ping-pong.
+const FRAME_TAG_HELLO: u8 = 0;
+const FRAME_TAG_DATA: u8 = 1;
+const FRAME_TAG_HELLO_ACK: u8 = 2;
+
+// consecutive inner-transport recv failures tolerated before the demux task
+// gives up retrying and exits instead of busy-spinning forever.
+const MAX_CONSECUTIVE_RECV_FAILURES: u32 = 32;
+
+/// wraps an inner `PeerRpcManagerTransport`, negotiating a `TransportSecurity`
+/// session with each peer on first contact and sealing/opening every message
+/// that crosses it. Handshake hellos and sealed data frames are multiplexed
+/// over the same underlying `send`/`recv` pair.
+pub struct SecureTransport<T> {
+    inner: Arc<T>,
+    security: Arc<dyn TransportSecurity>,
+    data_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<(uuid::Uuid, Bytes)>>,
+    session_ready: DashMap<uuid::Uuid, Arc<Notify>>,
+}
+
+impl<T: PeerRpcManagerTransport> SecureTransport<T> {
+    pub fn new(inner: T, security: Arc<dyn TransportSecurity>) -> Self {
+        let inner = Arc::new(inner);
+        let (data_tx, data_rx) = mpsc::unbounded_channel();
+
+        let demux_inner = inner.clone();
+        let demux_security = security.clone();
+        let session_ready: DashMap<uuid::Uuid, Arc<Notify>> = DashMap::new();
+        let demux_session_ready = session_ready.clone();
+        tokio::spawn(async move {
+            let mut consecutive_failures = 0u32;
+            loop {
+                let raw = match demux_inner.recv().await {
+                    Ok(raw) => {
+                        consecutive_failures = 0;
+                        raw
+                    }
+                    Err(e) => {
+                        consecutive_failures += 1;
+                        if consecutive_failures >= MAX_CONSECUTIVE_RECV_FAILURES {
+                            tracing::error!(error = ?e, consecutive_failures,
+                                "[RPC SECURITY] inner transport recv failed repeatedly, giving up on this transport");
+                            break;
+                        }
+                        tracing::warn!(error = ?e, consecutive_failures,
+                            "[RPC SECURITY] inner transport recv failed, backing off before retrying");
+                        // exponential backoff, capped, so a persistently dead
+                        // transport degrades to an occasional retry instead
+                        // of a 100%-cpu busy-spin.
+                        let backoff_ms = 10u64.saturating_mul(1u64 << consecutive_failures.min(10));
+                        tokio::time::sleep(std::time::Duration::from_millis(backoff_ms.min(5_000)))
+                            .await;
+                        continue;
+                    }
+                };
+                if raw.is_empty() {
+                    continue;
+                }
+                let mut raw = raw;
+                let tag = raw.get_u8();
+                match tag {
+                    FRAME_TAG_HELLO | FRAME_TAG_HELLO_ACK => {
+                        // the sender's peer id is prepended in front of its
+                        // hello payload so we know whose session this is.
+                        if raw.len() < 16 {
+                            tracing::error!("[RPC SECURITY] malformed hello frame, too short");
+                            continue;
+                        }
+                        let peer_id = uuid::Uuid::from_u128(raw.get_u128());
+                        if let Err(e) = demux_security.on_hello(peer_id, &raw) {
+                            tracing::error!(error = ?e, peer_id = ?peer_id, "[RPC SECURITY] handshake failed");
+                            continue;
+                        }
+                        tracing::info!(peer_id = ?peer_id, "[RPC SECURITY] session established");
+                        demux_session_ready
+                            .entry(peer_id)
+                            .or_insert_with(|| Arc::new(Notify::new()))
+                            .notify_waiters();
+
+                        // a fresh `HELLO` always gets an immediate ack carrying
+                        // our own hello, so the initiator's `ensure_session`
+                        // wakes up without waiting for it to send us any data
+                        // first. `HELLO_ACK` is terminal: it never triggers
+                        // another reply, or the two sides would ping-pong
+                        // hellos forever.
+                        if tag == FRAME_TAG_HELLO {
+                            let mut ack = BytesMut::new();
+                            ack.put_u8(FRAME_TAG_HELLO_ACK);
+                            ack.put_u128(demux_inner.my_peer_id().as_u128());
+                            ack.put_slice(&demux_security.make_hello());
+                            if let Err(e) = demux_inner.send(ack.freeze(), &peer_id).await {
+                                tracing::error!(error = ?e, peer_id = ?peer_id, "[RPC SECURITY] send hello ack failed");
+                            }
+                        }
+                    }
+                    FRAME_TAG_DATA => {
+                        if raw.len() < 16 {
+                            tracing::error!("[RPC SECURITY] malformed data frame, too short");
+                            continue;
+                        }
+                        let peer_id = uuid::Uuid::from_u128(raw.get_u128());
+                        if data_tx.send((peer_id, raw)).is_err() {
+                            tracing::warn!(
+                                "[RPC SECURITY] data receiver dropped, discarding frame"
+                            );
+                        }
+                    }
+                    _ => tracing::error!(tag, "[RPC SECURITY] unknown frame tag"),
+                }
+            }
+        });
+
+        Self {
+            inner,
+            security,
+            data_rx: tokio::sync::Mutex::new(data_rx),
+            session_ready,
+        }
+    }
+
+    async fn ensure_session(&self, peer_id: &uuid::Uuid) -> Result<(), Error> {
+        if self.security.has_session(peer_id) {
+            return Ok(());
+        }
+
+        let notify = self
+            .session_ready
+            .entry(*peer_id)
+            .or_insert_with(|| Arc::new(Notify::new()))
+            .clone();
+
+        let mut hello = BytesMut::new();
+        hello.put_u8(FRAME_TAG_HELLO);
+        hello.put_u128(self.inner.my_peer_id().as_u128());
+        hello.put_slice(&self.security.make_hello());
+        self.inner.send(hello.freeze(), peer_id).await?;
+
+        // `notify_waiters` only wakes listeners registered by the time it
+        // fires, so re-check after every wakeup instead of assuming a single
+        // notification means the session is ready (handles the handshake
+        // completing concurrently with us subscribing).
+        while !self.security.has_session(peer_id) {
+            let notified = notify.notified();
+            if self.security.has_session(peer_id) {
+                break;
+            }
+            let _ = tokio::time::timeout(std::time::Duration::from_secs(5), notified).await;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: PeerRpcManagerTransport> PeerRpcManagerTransport for SecureTransport<T> {
+    fn my_peer_id(&self) -> uuid::Uuid {
+        self.inner.my_peer_id()
+    }
+
+    async fn send(&self, msg: Bytes, dst_peer_id: &uuid::Uuid) -> Result<(), Error> {
+        self.ensure_session(dst_peer_id).await?;
+
+        let sealed = self.security.seal(dst_peer_id, msg)?;
+        let mut framed = BytesMut::with_capacity(sealed.len() + 17);
+        framed.put_u8(FRAME_TAG_DATA);
+        framed.put_u128(self.inner.my_peer_id().as_u128());
+        framed.put_slice(&sealed);
+
+        self.inner.send(framed.freeze(), dst_peer_id).await
+    }
+
+    async fn recv(&self) -> Result<Bytes, Error> {
+        let (src_peer_id, sealed) = self
+            .data_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::Unknown)?;
+        self.security.open(&src_peer_id, sealed)
+    }
+
+    fn get_next_hop(&self, dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid> {
+        self.inner.get_next_hop(dst_peer_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio_util::bytes::Bytes;
+
+    use crate::{
+        common::error::Error,
+        tunnels::{self, ring_tunnel::create_ring_tunnel_pair},
+    };
+
+    use super::{
+        AuthenticatedSecurity, EncryptedSecurity, PeerRpcManagerTransport, PlaintextSecurity,
+        SecureTransport, TransportSecurity,
+    };
+
+    #[test]
+    fn plaintext_security_is_a_no_op() {
+        let security = PlaintextSecurity;
+        let peer_id = uuid::Uuid::new_v4();
+        let payload = Bytes::from_static(b"hello");
+
+        assert!(security.has_session(&peer_id));
+        let sealed = security.seal(&peer_id, payload.clone()).unwrap();
+        assert_eq!(sealed, payload);
+        let opened = security.open(&peer_id, sealed).unwrap();
+        assert_eq!(opened, payload);
+    }
+
+    #[test]
+    fn encrypted_security_round_trips_after_handshake() {
+        let a = EncryptedSecurity::new(true);
+        let b = EncryptedSecurity::new(true);
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+
+        assert!(!a.has_session(&b_id));
+        a.on_hello(b_id, &b.make_hello()).unwrap();
+        b.on_hello(a_id, &a.make_hello()).unwrap();
+        assert!(a.has_session(&b_id));
+        assert!(b.has_session(&a_id));
+
+        let payload = Bytes::from_static(b"super secret rpc payload");
+        let sealed = a.seal(&b_id, payload.clone()).unwrap();
+        assert_ne!(sealed.as_ref(), payload.as_ref());
+        let opened = b.open(&a_id, sealed).unwrap();
+        assert_eq!(opened, payload);
+    }
+
+    #[test]
+    fn encrypted_security_rejects_payload_sealed_for_a_different_session() {
+        let a = EncryptedSecurity::new(false);
+        let b = EncryptedSecurity::new(false);
+        let c = EncryptedSecurity::new(false);
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+        let c_id = uuid::Uuid::new_v4();
+
+        a.on_hello(b_id, &b.make_hello()).unwrap();
+        b.on_hello(a_id, &a.make_hello()).unwrap();
+        a.on_hello(c_id, &c.make_hello()).unwrap();
+        c.on_hello(a_id, &a.make_hello()).unwrap();
+
+        let sealed_for_b = a.seal(&b_id, Bytes::from_static(b"for b only")).unwrap();
+        assert!(c.open(&a_id, sealed_for_b).is_err());
+    }
+
+    #[test]
+    fn authenticated_security_round_trips_after_handshake() {
+        let a = AuthenticatedSecurity::new(true);
+        let b = AuthenticatedSecurity::new(true);
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+
+        assert!(!a.has_session(&b_id));
+        a.on_hello(b_id, &b.make_hello()).unwrap();
+        b.on_hello(a_id, &a.make_hello()).unwrap();
+        assert!(a.has_session(&b_id));
+        assert!(b.has_session(&a_id));
+
+        let payload = Bytes::from_static(b"authenticated but not secret rpc payload");
+        let sealed = a.seal(&b_id, payload.clone()).unwrap();
+        // unlike `EncryptedSecurity`, the payload itself is not hidden - only
+        // tagged - so it still appears verbatim inside the tagged frame.
+        assert!(sealed.windows(payload.len()).any(|w| w == payload.as_ref()));
+        let opened = b.open(&a_id, sealed).unwrap();
+        assert_eq!(opened, payload);
+    }
+
+    #[test]
+    fn authenticated_security_detects_tampering() {
+        let a = AuthenticatedSecurity::new(false);
+        let b = AuthenticatedSecurity::new(false);
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+
+        a.on_hello(b_id, &b.make_hello()).unwrap();
+        b.on_hello(a_id, &a.make_hello()).unwrap();
+
+        let mut sealed = a
+            .seal(&b_id, Bytes::from_static(b"untampered"))
+            .unwrap()
+            .to_vec();
+        // flip a bit in the payload, past the 32-byte hmac tag prefix.
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0x01;
+
+        assert!(b.open(&a_id, Bytes::from(sealed)).is_err());
+    }
+
+    #[test]
+    fn authenticated_security_rejects_payload_tagged_for_a_different_session() {
+        let a = AuthenticatedSecurity::new(false);
+        let b = AuthenticatedSecurity::new(false);
+        let c = AuthenticatedSecurity::new(false);
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+        let c_id = uuid::Uuid::new_v4();
+
+        a.on_hello(b_id, &b.make_hello()).unwrap();
+        b.on_hello(a_id, &a.make_hello()).unwrap();
+        a.on_hello(c_id, &c.make_hello()).unwrap();
+        c.on_hello(a_id, &a.make_hello()).unwrap();
+
+        let tagged_for_b = a.seal(&b_id, Bytes::from_static(b"for b only")).unwrap();
+        assert!(c.open(&a_id, tagged_for_b).is_err());
+    }
+
+    struct MockTransport {
+        tunnel: Box<dyn tunnels::Tunnel>,
+        my_peer_id: uuid::Uuid,
+    }
+
+    #[async_trait::async_trait]
+    impl PeerRpcManagerTransport for MockTransport {
+        fn my_peer_id(&self) -> uuid::Uuid {
+            self.my_peer_id
+        }
+        async fn send(&self, msg: Bytes, _dst_peer_id: &uuid::Uuid) -> Result<(), Error> {
+            self.tunnel.pin_sink().send(msg).await.unwrap();
+            Ok(())
+        }
+        async fn recv(&self) -> Result<Bytes, Error> {
+            let ret = self.tunnel.pin_stream().next().await.unwrap();
+            ret.map(|v| v.freeze()).map_err(|_| Error::Unknown)
+        }
+        fn get_next_hop(&self, dst_peer_id: &uuid::Uuid) -> Option<uuid::Uuid> {
+            Some(*dst_peer_id)
+        }
+    }
+
+    // end-to-end: the handshake must complete from a single round trip even
+    // though the receiving side never independently decides to talk to the
+    // sender first; this is the scenario that deadlocked before `HELLO_ACK`
+    // was added.
+    #[tokio::test]
+    async fn secure_transport_handshake_does_not_deadlock() {
+        let (ct, st) = create_ring_tunnel_pair();
+        let a_id = uuid::Uuid::new_v4();
+        let b_id = uuid::Uuid::new_v4();
+
+        let a = SecureTransport::new(
+            MockTransport {
+                tunnel: ct,
+                my_peer_id: a_id,
+            },
+            std::sync::Arc::new(EncryptedSecurity::new(true)),
+        );
+        let b = SecureTransport::new(
+            MockTransport {
+                tunnel: st,
+                my_peer_id: b_id,
+            },
+            std::sync::Arc::new(EncryptedSecurity::new(true)),
+        );
+
+        let payload = Bytes::from_static(b"hello from a, encrypted");
+        let (send_ret, recv_ret) = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            futures::future::join(a.send(payload.clone(), &b_id), b.recv()),
+        )
+        .await
+        .expect("handshake + data round trip should not hang");
+
+        send_ret.unwrap();
+        assert_eq!(recv_ret.unwrap(), payload);
+    }
+}